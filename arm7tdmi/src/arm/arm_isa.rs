@@ -9,6 +9,13 @@ pub enum ArmError {
     UnknownInstructionFormat(u32),
     UndefinedConditionCode(u32),
     InvalidShiftType(u32),
+    /// A field passed to an encoder didn't fit in the bits allotted to it.
+    /// The `&'static str` names the field (e.g. "rd", "branch offset").
+    FieldOverflow(&'static str, u32),
+    /// An `ArmInstructionShiftValue` variant that has no valid encoding in this field,
+    /// regardless of its value (e.g. a `RegisterShift` in a single data transfer offset).
+    /// The `&'static str` names the field (e.g. "offset").
+    UnsupportedOperand(&'static str),
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Primitive)]
@@ -30,6 +37,30 @@ pub enum ArmCond {
     Always = 0b1110,
 }
 
+impl ArmCond {
+    /// Evaluate this condition against the NZCV flags of the processor's current PSR.
+    pub fn passes(&self, n: bool, z: bool, c: bool, v: bool) -> bool {
+        use ArmCond::*;
+        match self {
+            Equal => z,
+            NotEqual => !z,
+            UnsignedHigherOrSame => c,
+            UnsignedLower => !c,
+            Negative => n,
+            PositiveOrZero => !n,
+            Overflow => v,
+            NoOverflow => !v,
+            UnsignedHigher => c && !z,
+            UnsignedLowerOrSame => !c || z,
+            GreaterOrEqual => n == v,
+            LessThan => n != v,
+            GreaterThan => !z && (n == v),
+            LessThanOrEqual => z || (n != v),
+            Always => true,
+        }
+    }
+}
+
 #[derive(Debug, Copy, Clone, PartialEq)]
 #[allow(non_camel_case_types)]
 pub enum ArmInstructionFormat {
@@ -59,9 +90,19 @@ pub enum ArmInstructionFormat {
     MSR_REG,
     // Tanssfer immediate/register to PSR flags only
     MSR_FLAGS,
+    // Software Interrupt
+    SWI,
+    // Coprocessor Data Operation
+    CDP,
+    // Coprocessor Data Transfer
+    LDC_STC,
+    // Coprocessor Register Transfer
+    MRC_MCR,
+    // Undefined instruction
+    Undefined,
 }
 
-#[derive(Debug, Primitive)]
+#[derive(Debug, PartialEq, Primitive)]
 pub enum ArmOpCode {
     AND = 0b0000,
     EOR = 0b0001,
@@ -110,6 +151,18 @@ impl TryFrom<(u32, u32)> for ArmInstruction {
             Ok(MUL_MLA)
         } else if (0x0f80_00f0 & raw) == 0x0080_0090 {
             Ok(MULL_MLAL)
+        } else if (0x0f00_0000 & raw) == 0x0f00_0000 {
+            Ok(SWI)
+        } else if (0x0e00_0010 & raw) == 0x0600_0010 {
+            Ok(Undefined)
+        } else if (0x0f00_0010 & raw) == 0x0e00_0010 {
+            Ok(MRC_MCR)
+        } else if (0x0f00_0010 & raw) == 0x0e00_0000 {
+            Ok(CDP)
+        } else if (0x0e00_0000 & raw) == 0x0c00_0000 {
+            Ok(LDC_STC)
+        } else if (0x0fb0_0ff0 & raw) == 0x0100_0090 {
+            Ok(SWP)
         } else if (0x0c00_0000 & raw) == 0x0400_0000 {
             Ok(LDR_STR)
         } else if (0x0e40_0F90 & raw) == 0x0000_0090 {
@@ -143,7 +196,7 @@ impl TryFrom<(u32, u32)> for ArmInstruction {
     }
 }
 
-#[derive(Debug, PartialEq, Primitive)]
+#[derive(Debug, Copy, Clone, PartialEq, Primitive)]
 pub enum ArmShiftType {
     LSL = 0,
     LSR = 1,
@@ -182,6 +235,15 @@ pub enum ArmInstructionShiftValue {
     ShiftedRegister(usize, ArmShift),
 }
 
+/// The offset field of a halfword/signed data transfer instruction
+/// (`ArmInstructionFormat::LDR_STR_HS_REG`/`LDR_STR_HS_IMM`). Unlike the single data
+/// transfer offset, the register form carries no shift.
+#[derive(Debug, PartialEq)]
+pub enum ArmHalfwordOffset {
+    Immediate(u32),
+    Register(usize),
+}
+
 impl ArmInstructionShiftValue {
     /// Decode operand2 as an immediate value
     pub fn decode_rotated_immediate(&self) -> Option<i32> {
@@ -192,6 +254,23 @@ impl ArmInstructionShiftValue {
     }
 }
 
+/// Find an `(imm8, rot)` pair encoding `value` as a data-processing rotated immediate,
+/// i.e. such that `imm8.rotate_right(rot) == value`. `rot` is already doubled (even, in
+/// `0..=30`) to match the convention used by `ArmInstructionShiftValue::RotatedImmediate`
+/// elsewhere in this module (see `decode_rotated_immediate`, `try_operand2`), so the
+/// result can be fed straight into `RotatedImmediate(imm8, rot)`. Returns `None` if
+/// `value` isn't representable as an 8-bit value rotated right by an even amount, in
+/// which case a MOV/ORR (or similar) instruction sequence is required instead.
+pub fn encode_rotated_immediate(value: u32) -> Option<(u32, u32)> {
+    for r in 0..16 {
+        let candidate = value.rotate_left(2 * r);
+        if candidate <= 0xFF {
+            return Some((candidate, 2 * r));
+        }
+    }
+    None
+}
+
 impl ArmInstruction {
     pub fn rn(&self) -> usize {
         match self.fmt {
@@ -261,30 +340,70 @@ impl ArmInstruction {
         self.raw.bit(24)
     }
 
-    pub fn offset(&self) -> ArmInstructionShiftValue {
+    /// Fallible version of [`ArmInstruction::offset`]. Never panics on malformed input.
+    pub fn try_offset(&self) -> Result<ArmInstructionShiftValue, ArmError> {
         let ofs = self.raw.bit_range(0..12);
         if self.raw.bit(25) {
             let rm = ofs & 0xf;
-            let shift = ArmShift::try_from(ofs).unwrap();
-            ArmInstructionShiftValue::ShiftedRegister(rm as usize, shift)
+            let shift = ArmShift::try_from(ofs)?;
+            Ok(ArmInstructionShiftValue::ShiftedRegister(rm as usize, shift))
         } else {
-            ArmInstructionShiftValue::ImmediateValue(ofs)
+            Ok(ArmInstructionShiftValue::ImmediateValue(ofs))
         }
     }
 
-    pub fn operand2(&self) -> ArmInstructionShiftValue {
+    /// The offset field of a single data transfer instruction.
+    ///
+    /// The shift-type field of an offset is always a 2-bit value, and every 2-bit value
+    /// is a valid `ArmShiftType`, so this can never panic in practice; use
+    /// [`ArmInstruction::try_offset`] if that invariant ever needs to be load-bearing.
+    pub fn offset(&self) -> ArmInstructionShiftValue {
+        self.try_offset().expect("offset shift type is always valid")
+    }
+
+    /// Fallible version of [`ArmInstruction::operand2`]. Never panics on malformed input.
+    pub fn try_operand2(&self) -> Result<ArmInstructionShiftValue, ArmError> {
         let op2 = self.raw.bit_range(0..12);
         if self.raw.bit(25) {
             let immediate = op2 & 0xff;
             let rotate = 2 * op2.bit_range(8..12);
-            ArmInstructionShiftValue::RotatedImmediate(immediate, rotate)
+            Ok(ArmInstructionShiftValue::RotatedImmediate(immediate, rotate))
         } else {
             let reg = op2 & 0xf;
-            let shift = ArmShift::try_from(op2).unwrap(); // TODO error handling
-            ArmInstructionShiftValue::ShiftedRegister(reg as usize, shift)
+            let shift = ArmShift::try_from(op2)?;
+            Ok(ArmInstructionShiftValue::ShiftedRegister(reg as usize, shift))
         }
     }
 
+    /// The `operand2` field of a data-processing instruction.
+    ///
+    /// The shift-type field of operand2 is always a 2-bit value, and every 2-bit value is
+    /// a valid `ArmShiftType`, so this can never panic in practice; use
+    /// [`ArmInstruction::try_operand2`] if that invariant ever needs to be load-bearing.
+    pub fn operand2(&self) -> ArmInstructionShiftValue {
+        self.try_operand2().expect("operand2 shift type is always valid")
+    }
+
+    /// The comment field of a software interrupt instruction (bits 0..24).
+    pub fn swi_comment(&self) -> u32 {
+        self.raw.bit_range(0..24)
+    }
+
+    /// The coprocessor number (`cp_num`) of a coprocessor instruction, e.g. `p14`.
+    pub fn cp_num(&self) -> usize {
+        self.raw.bit_range(8..12) as usize
+    }
+
+    /// The coprocessor opcode of a `CDP` instruction (bits 20..24).
+    pub fn cp_opcode(&self) -> u32 {
+        self.raw.bit_range(20..24)
+    }
+
+    /// The coprocessor information field of a `MRC`/`MCR` instruction (bits 21..24).
+    pub fn cp_info(&self) -> u32 {
+        self.raw.bit_range(21..24)
+    }
+
     pub fn register_list(&self) -> Vec<usize> {
         let list_bits = self.raw & 0xffff;
         let mut list = Vec::with_capacity(16);
@@ -295,4 +414,788 @@ impl ArmInstruction {
         }
         list
     }
+
+    /// The `Rs` operand of a multiply (`MUL_MLA`) or multiply-long (`MULL_MLAL`) instruction.
+    pub fn rs(&self) -> usize {
+        self.raw.bit_range(8..12) as usize
+    }
+
+    /// The `RdHi` operand of a multiply-long instruction (`ArmInstructionFormat::MULL_MLAL`).
+    pub fn rd_hi(&self) -> usize {
+        self.raw.bit_range(16..20) as usize
+    }
+
+    /// The accumulate (`A`) bit of a multiply or multiply-long instruction.
+    pub fn is_accumulate(&self) -> bool {
+        self.raw.bit(21)
+    }
+
+    /// Whether this instruction operates on signed operands: the `U`/signed bit of a
+    /// multiply-long instruction, or the `S` bit of a halfword/signed data transfer.
+    pub fn is_signed(&self) -> bool {
+        match self.fmt {
+            ArmInstructionFormat::MULL_MLAL => self.raw.bit(22),
+            _ => self.raw.bit(6),
+        }
+    }
+
+    /// The `H` bit of a halfword/signed data transfer instruction: set for a halfword
+    /// transfer, clear for a (signed) byte transfer.
+    pub fn is_halfword_transfer(&self) -> bool {
+        self.raw.bit(5)
+    }
+
+    /// The offset field of a halfword/signed data transfer instruction.
+    pub fn hs_offset(&self) -> ArmHalfwordOffset {
+        if self.raw.bit(22) {
+            let hi = self.raw.bit_range(8..12);
+            let lo = self.raw.bit_range(0..4);
+            ArmHalfwordOffset::Immediate((hi << 4) | lo)
+        } else {
+            ArmHalfwordOffset::Register(self.rm())
+        }
+    }
+}
+
+fn check_reg(name: &'static str, r: usize) -> Result<u32, ArmError> {
+    if r > 0xf {
+        Err(ArmError::FieldOverflow(name, r as u32))
+    } else {
+        Ok(r as u32)
+    }
+}
+
+fn encode_shift(shift: &ArmShift) -> Result<u32, ArmError> {
+    match shift {
+        ArmShift::ImmediateShift(amount, typ) => {
+            if *amount > 0x1f {
+                return Err(ArmError::FieldOverflow("shift amount", *amount));
+            }
+            Ok((amount << 7) | ((*typ as u32) << 5))
+        }
+        ArmShift::RegisterShift(rs, typ) => {
+            let rs = check_reg("shift register", *rs)?;
+            Ok((rs << 8) | ((*typ as u32) << 5) | (1 << 4))
+        }
+    }
+}
+
+/// Encode an `ArmInstructionShiftValue` as it appears in the `operand2` field of a
+/// data-processing instruction, returning the 12-bit field and whether the immediate
+/// (`I`) bit should be set.
+fn encode_operand2(operand2: &ArmInstructionShiftValue) -> Result<(bool, u32), ArmError> {
+    match operand2 {
+        ArmInstructionShiftValue::RotatedImmediate(imm, rot) => {
+            if *imm > 0xff {
+                return Err(ArmError::FieldOverflow("operand2 immediate", *imm));
+            }
+            if *rot > 30 || rot % 2 != 0 {
+                return Err(ArmError::FieldOverflow("operand2 rotate", *rot));
+            }
+            Ok((true, (imm & 0xff) | ((rot / 2) << 8)))
+        }
+        ArmInstructionShiftValue::ShiftedRegister(rm, shift) => {
+            let rm = check_reg("operand2 rm", *rm)?;
+            Ok((false, encode_shift(shift)? | rm))
+        }
+        ArmInstructionShiftValue::ImmediateValue(_) => {
+            Err(ArmError::UnsupportedOperand("operand2 immediate"))
+        }
+    }
+}
+
+/// Encode an `ArmInstructionShiftValue` as it appears in the `offset` field of a
+/// single data transfer instruction, returning the 12-bit field and whether the
+/// offset-is-a-register (`I`) bit should be set.
+fn encode_offset(offset: &ArmInstructionShiftValue) -> Result<(bool, u32), ArmError> {
+    match offset {
+        ArmInstructionShiftValue::ImmediateValue(ofs) => {
+            if *ofs > 0xfff {
+                return Err(ArmError::FieldOverflow("offset immediate", *ofs));
+            }
+            Ok((false, *ofs))
+        }
+        ArmInstructionShiftValue::ShiftedRegister(_rm, ArmShift::RegisterShift(..)) => {
+            // offset-is-register (bit25=1) with a register-specified shift amount (bit4=1)
+            // is the bit pattern the decoder reserves for `ArmInstructionFormat::Undefined`;
+            // no real LDR_STR ever carries a `RegisterShift` in the offset position.
+            Err(ArmError::UnsupportedOperand("offset shift register"))
+        }
+        ArmInstructionShiftValue::ShiftedRegister(rm, shift) => {
+            let rm = check_reg("offset rm", *rm)?;
+            Ok((true, encode_shift(shift)? | rm))
+        }
+        ArmInstructionShiftValue::RotatedImmediate(..) => {
+            Err(ArmError::UnsupportedOperand("offset immediate"))
+        }
+    }
+}
+
+// Encoders below cover every `ArmInstructionFormat` variant that has a well-defined
+// assembled form (`SWI`/`CDP`/`LDC_STC`/`MRC_MCR`/`Undefined` are decode-only: there is
+// no encoder for them here, and `Undefined` is not a real instruction to assemble).
+
+/// Assemble a Data Processing instruction (`ArmInstructionFormat::DP`).
+pub fn encode_dp(
+    cond: ArmCond,
+    opcode: ArmOpCode,
+    set_cond: bool,
+    rn: usize,
+    rd: usize,
+    operand2: ArmInstructionShiftValue,
+) -> Result<u32, ArmError> {
+    use ArmOpCode::*;
+    if !set_cond && matches!(opcode, TST | TEQ | CMP | CMN) {
+        // TST/TEQ/CMP/CMN with S=0 is the bit pattern the decoder reserves for
+        // MRS/MSR_REG/MSR_FLAGS; there's no DP instruction to round-trip here.
+        return Err(ArmError::FieldOverflow("set_cond", set_cond as u32));
+    }
+    let rn = check_reg("rn", rn)?;
+    let rd = check_reg("rd", rd)?;
+    let (immediate, op2) = encode_operand2(&operand2)?;
+    Ok(((cond as u32) << 28)
+        | ((immediate as u32) << 25)
+        | ((opcode as u32) << 21)
+        | ((set_cond as u32) << 20)
+        | (rn << 16)
+        | (rd << 12)
+        | op2)
+}
+
+/// Assemble a Single Data Transfer instruction (`ArmInstructionFormat::LDR_STR`).
+pub fn encode_ldr_str(
+    cond: ArmCond,
+    is_load: bool,
+    is_pre_indexing: bool,
+    is_ofs_added: bool,
+    transfer_size: usize,
+    is_write_back: bool,
+    rn: usize,
+    rd: usize,
+    offset: ArmInstructionShiftValue,
+) -> Result<u32, ArmError> {
+    let rn = check_reg("rn", rn)?;
+    let rd = check_reg("rd", rd)?;
+    let (is_register_offset, ofs) = encode_offset(&offset)?;
+    let byte_transfer = match transfer_size {
+        1 => true,
+        4 => false,
+        _ => return Err(ArmError::FieldOverflow("transfer_size", transfer_size as u32)),
+    };
+    Ok(0x0400_0000
+        | ((cond as u32) << 28)
+        | ((is_register_offset as u32) << 25)
+        | ((is_pre_indexing as u32) << 24)
+        | ((is_ofs_added as u32) << 23)
+        | ((byte_transfer as u32) << 22)
+        | ((is_write_back as u32) << 21)
+        | ((is_load as u32) << 20)
+        | (rn << 16)
+        | (rd << 12)
+        | ofs)
+}
+
+/// Assemble a Branch / Branch-with-Link instruction (`ArmInstructionFormat::B_BL`).
+///
+/// `offset` is the absolute target address relative to the instruction's own `pc`, i.e.
+/// the same value that [`ArmInstruction::branch_offset`] would return.
+pub fn encode_b_bl(cond: ArmCond, link: bool, offset: i32) -> Result<u32, ArmError> {
+    let relative = offset
+        .checked_sub(8)
+        .ok_or(ArmError::FieldOverflow("branch offset", offset as u32))?;
+    if relative % 4 != 0 {
+        return Err(ArmError::FieldOverflow("branch offset", offset as u32));
+    }
+    let word_offset = relative >> 2;
+    if word_offset < -(1 << 23) || word_offset >= (1 << 23) {
+        return Err(ArmError::FieldOverflow("branch offset", offset as u32));
+    }
+    Ok(0x0a00_0000
+        | ((cond as u32) << 28)
+        | ((link as u32) << 24)
+        | (word_offset as u32 & 0x00ff_ffff))
+}
+
+/// Assemble a Branch and Exchange instruction (`ArmInstructionFormat::BX`).
+pub fn encode_bx(cond: ArmCond, rm: usize) -> Result<u32, ArmError> {
+    let rm = check_reg("rm", rm)?;
+    Ok(0x012f_ff10 | ((cond as u32) << 28) | rm)
+}
+
+/// Assemble a Multiply or Multiply-Accumulate instruction (`ArmInstructionFormat::MUL_MLA`).
+pub fn encode_mul_mla(
+    cond: ArmCond,
+    is_accumulate: bool,
+    set_cond: bool,
+    rd: usize,
+    rn: usize,
+    rs: usize,
+    rm: usize,
+) -> Result<u32, ArmError> {
+    let rd = check_reg("rd", rd)?;
+    let rn = check_reg("rn", rn)?;
+    let rs = check_reg("rs", rs)?;
+    let rm = check_reg("rm", rm)?;
+    Ok(0x0000_0090
+        | ((cond as u32) << 28)
+        | ((is_accumulate as u32) << 21)
+        | ((set_cond as u32) << 20)
+        | (rd << 16)
+        | (rn << 12)
+        | (rs << 8)
+        | rm)
+}
+
+/// Assemble a Multiply Long or Multiply-Accumulate Long instruction
+/// (`ArmInstructionFormat::MULL_MLAL`).
+pub fn encode_mull_mlal(
+    cond: ArmCond,
+    is_signed: bool,
+    is_accumulate: bool,
+    set_cond: bool,
+    rd_hi: usize,
+    rd_lo: usize,
+    rs: usize,
+    rm: usize,
+) -> Result<u32, ArmError> {
+    let rd_hi = check_reg("rd_hi", rd_hi)?;
+    let rd_lo = check_reg("rd_lo", rd_lo)?;
+    let rs = check_reg("rs", rs)?;
+    let rm = check_reg("rm", rm)?;
+    Ok(0x0080_0090
+        | ((cond as u32) << 28)
+        | ((is_signed as u32) << 22)
+        | ((is_accumulate as u32) << 21)
+        | ((set_cond as u32) << 20)
+        | (rd_hi << 16)
+        | (rd_lo << 12)
+        | (rs << 8)
+        | rm)
+}
+
+/// Encode an `ArmHalfwordOffset` as it appears in the offset field of a halfword/signed
+/// data transfer instruction, returning the field (an immediate already split into the
+/// instruction's offset-hi (bits 8..12) and offset-lo (bits 0..4) nibbles) and whether
+/// the immediate (`I`) bit should be set.
+fn encode_hs_offset(offset: &ArmHalfwordOffset) -> Result<(bool, u32), ArmError> {
+    match offset {
+        ArmHalfwordOffset::Immediate(v) => {
+            if *v > 0xff {
+                return Err(ArmError::FieldOverflow("halfword offset immediate", *v));
+            }
+            Ok((true, ((v & 0xf0) << 4) | (v & 0xf)))
+        }
+        ArmHalfwordOffset::Register(rm) => {
+            let rm = check_reg("halfword offset rm", *rm)?;
+            Ok((false, rm))
+        }
+    }
+}
+
+/// Assemble a Halfword/Signed Data Transfer instruction
+/// (`ArmInstructionFormat::LDR_STR_HS_REG`/`LDR_STR_HS_IMM`).
+pub fn encode_ldr_str_hs(
+    cond: ArmCond,
+    is_load: bool,
+    is_pre_indexing: bool,
+    is_ofs_added: bool,
+    is_write_back: bool,
+    is_signed: bool,
+    is_halfword: bool,
+    rn: usize,
+    rd: usize,
+    offset: ArmHalfwordOffset,
+) -> Result<u32, ArmError> {
+    let rn = check_reg("rn", rn)?;
+    let rd = check_reg("rd", rd)?;
+    let (is_immediate, ofs) = encode_hs_offset(&offset)?;
+    Ok(0x0000_0090
+        | ((cond as u32) << 28)
+        | ((is_pre_indexing as u32) << 24)
+        | ((is_ofs_added as u32) << 23)
+        | ((is_immediate as u32) << 22)
+        | ((is_write_back as u32) << 21)
+        | ((is_load as u32) << 20)
+        | (rn << 16)
+        | (rd << 12)
+        | ((is_signed as u32) << 6)
+        | ((is_halfword as u32) << 5)
+        | ofs)
+}
+
+/// Assemble a Block Data Transfer instruction (`ArmInstructionFormat::LDM_STM`).
+pub fn encode_ldm_stm(
+    cond: ArmCond,
+    is_load: bool,
+    is_pre_indexing: bool,
+    is_ofs_added: bool,
+    is_psr_and_force_user_mode: bool,
+    is_write_back: bool,
+    rn: usize,
+    register_list: &[usize],
+) -> Result<u32, ArmError> {
+    let rn = check_reg("rn", rn)?;
+    let mut list_bits: u32 = 0;
+    for &r in register_list {
+        let r = check_reg("register list entry", r)?;
+        list_bits |= 1 << r;
+    }
+    Ok(0x0800_0000
+        | ((cond as u32) << 28)
+        | ((is_pre_indexing as u32) << 24)
+        | ((is_ofs_added as u32) << 23)
+        | ((is_psr_and_force_user_mode as u32) << 22)
+        | ((is_write_back as u32) << 21)
+        | ((is_load as u32) << 20)
+        | (rn << 16)
+        | list_bits)
+}
+
+/// Assemble a Single Data Swap instruction (`ArmInstructionFormat::SWP`).
+pub fn encode_swp(
+    cond: ArmCond,
+    transfer_size: usize,
+    rn: usize,
+    rd: usize,
+    rm: usize,
+) -> Result<u32, ArmError> {
+    let rn = check_reg("rn", rn)?;
+    let rd = check_reg("rd", rd)?;
+    let rm = check_reg("rm", rm)?;
+    let byte_transfer = match transfer_size {
+        1 => true,
+        4 => false,
+        _ => return Err(ArmError::FieldOverflow("transfer_size", transfer_size as u32)),
+    };
+    Ok(0x0100_0090
+        | ((cond as u32) << 28)
+        | ((byte_transfer as u32) << 22)
+        | (rn << 16)
+        | (rd << 12)
+        | rm)
+}
+
+/// Assemble an instruction transferring PSR contents to a register
+/// (`ArmInstructionFormat::MRS`).
+pub fn encode_mrs(cond: ArmCond, is_spsr: bool, rd: usize) -> Result<u32, ArmError> {
+    let rd = check_reg("rd", rd)?;
+    Ok(0x010f_0000 | ((cond as u32) << 28) | ((is_spsr as u32) << 22) | (rd << 12))
+}
+
+/// Assemble an instruction transferring a register to the full PSR
+/// (`ArmInstructionFormat::MSR_REG`).
+pub fn encode_msr_reg(cond: ArmCond, is_spsr: bool, rm: usize) -> Result<u32, ArmError> {
+    let rm = check_reg("rm", rm)?;
+    Ok(0x0129_f000 | ((cond as u32) << 28) | ((is_spsr as u32) << 22) | rm)
+}
+
+/// Assemble an instruction transferring an immediate or register value to the condition
+/// code flags only (`ArmInstructionFormat::MSR_FLAGS`).
+pub fn encode_msr_flags(
+    cond: ArmCond,
+    is_spsr: bool,
+    operand2: ArmInstructionShiftValue,
+) -> Result<u32, ArmError> {
+    let (immediate, op2) = encode_operand2(&operand2)?;
+    Ok(0x0128_f000
+        | ((cond as u32) << 28)
+        | ((immediate as u32) << 25)
+        | ((is_spsr as u32) << 22)
+        | op2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dp_round_trips_with_shifted_register_operand2() {
+        let operand2 = ArmInstructionShiftValue::ShiftedRegister(
+            3,
+            ArmShift::ImmediateShift(5, ArmShiftType::LSL),
+        );
+        let raw = encode_dp(ArmCond::Always, ArmOpCode::ADD, true, 1, 2, operand2).unwrap();
+        let decoded = ArmInstruction::try_from((raw, 0)).unwrap();
+        assert_eq!(decoded.fmt, ArmInstructionFormat::DP);
+        assert_eq!(decoded.cond, ArmCond::Always);
+        assert_eq!(decoded.opcode(), Some(ArmOpCode::ADD));
+        assert_eq!(decoded.rn(), 1);
+        assert_eq!(decoded.rd(), 2);
+        assert!(decoded.is_set_cond());
+        assert_eq!(
+            decoded.operand2(),
+            ArmInstructionShiftValue::ShiftedRegister(3, ArmShift::ImmediateShift(5, ArmShiftType::LSL))
+        );
+    }
+
+    #[test]
+    fn dp_round_trips_with_rotated_immediate_operand2() {
+        let operand2 = ArmInstructionShiftValue::RotatedImmediate(0xab, 4);
+        let raw = encode_dp(ArmCond::Equal, ArmOpCode::MOV, false, 0, 7, operand2).unwrap();
+        let decoded = ArmInstruction::try_from((raw, 0)).unwrap();
+        assert_eq!(decoded.fmt, ArmInstructionFormat::DP);
+        assert_eq!(decoded.rd(), 7);
+        assert_eq!(
+            decoded.operand2(),
+            ArmInstructionShiftValue::RotatedImmediate(0xab, 4)
+        );
+    }
+
+    #[test]
+    fn ldr_str_round_trips_with_immediate_offset() {
+        let offset = ArmInstructionShiftValue::ImmediateValue(0x20);
+        let raw =
+            encode_ldr_str(ArmCond::Always, true, true, true, 4, false, 5, 6, offset).unwrap();
+        let decoded = ArmInstruction::try_from((raw, 0)).unwrap();
+        assert_eq!(decoded.fmt, ArmInstructionFormat::LDR_STR);
+        assert!(decoded.is_load());
+        assert!(decoded.is_pre_indexing());
+        assert!(decoded.is_ofs_added());
+        assert_eq!(decoded.transfer_size(), 4);
+        assert_eq!(decoded.rn(), 5);
+        assert_eq!(decoded.rd(), 6);
+        assert_eq!(
+            decoded.offset(),
+            ArmInstructionShiftValue::ImmediateValue(0x20)
+        );
+    }
+
+    #[test]
+    fn ldr_str_round_trips_with_shifted_register_offset() {
+        let offset = ArmInstructionShiftValue::ShiftedRegister(
+            1,
+            ArmShift::ImmediateShift(2, ArmShiftType::LSL),
+        );
+        let raw =
+            encode_ldr_str(ArmCond::Always, true, true, true, 4, false, 5, 6, offset).unwrap();
+        let decoded = ArmInstruction::try_from((raw, 0)).unwrap();
+        assert_eq!(decoded.fmt, ArmInstructionFormat::LDR_STR);
+        assert_eq!(
+            decoded.offset(),
+            ArmInstructionShiftValue::ShiftedRegister(1, ArmShift::ImmediateShift(2, ArmShiftType::LSL))
+        );
+    }
+
+    #[test]
+    fn encode_ldr_str_rejects_register_shift_offset() {
+        // A register-specified shift amount in the offset position collides with the bit
+        // pattern the decoder reserves for `ArmInstructionFormat::Undefined`.
+        let offset = ArmInstructionShiftValue::ShiftedRegister(
+            1,
+            ArmShift::RegisterShift(2, ArmShiftType::LSL),
+        );
+        let err =
+            encode_ldr_str(ArmCond::Always, true, true, true, 4, false, 5, 6, offset).unwrap_err();
+        assert_eq!(err, ArmError::UnsupportedOperand("offset shift register"));
+    }
+
+    #[test]
+    fn encode_ldr_str_rejects_rotated_immediate_offset() {
+        // `RotatedImmediate` is the DP operand2 encoding; the LDR/STR offset field has no
+        // equivalent rotation, so this variant can never appear here.
+        let offset = ArmInstructionShiftValue::RotatedImmediate(0xab, 4);
+        let err =
+            encode_ldr_str(ArmCond::Always, true, true, true, 4, false, 5, 6, offset).unwrap_err();
+        assert_eq!(err, ArmError::UnsupportedOperand("offset immediate"));
+    }
+
+    #[test]
+    fn b_bl_round_trips() {
+        let raw = encode_b_bl(ArmCond::Always, true, 100).unwrap();
+        let decoded = ArmInstruction::try_from((raw, 0)).unwrap();
+        assert_eq!(decoded.fmt, ArmInstructionFormat::B_BL);
+        assert!(decoded.is_linked_branch());
+        assert_eq!(decoded.branch_offset(), 100);
+    }
+
+    #[test]
+    fn bx_round_trips() {
+        let raw = encode_bx(ArmCond::Always, 14).unwrap();
+        let decoded = ArmInstruction::try_from((raw, 0)).unwrap();
+        assert_eq!(decoded.fmt, ArmInstructionFormat::BX);
+        assert_eq!(decoded.rn(), 14);
+    }
+
+    #[test]
+    fn mul_mla_round_trips() {
+        let raw = encode_mul_mla(ArmCond::Always, true, true, 1, 2, 3, 4).unwrap();
+        let decoded = ArmInstruction::try_from((raw, 0)).unwrap();
+        assert_eq!(decoded.fmt, ArmInstructionFormat::MUL_MLA);
+        assert!(decoded.is_accumulate());
+        assert!(decoded.is_set_cond());
+        assert_eq!(decoded.rd(), 1);
+        assert_eq!(decoded.rn(), 2);
+        assert_eq!(decoded.rs(), 3);
+        assert_eq!(decoded.rm(), 4);
+    }
+
+    #[test]
+    fn mull_mlal_round_trips() {
+        let raw = encode_mull_mlal(ArmCond::Always, true, false, true, 1, 2, 3, 4).unwrap();
+        let decoded = ArmInstruction::try_from((raw, 0)).unwrap();
+        assert_eq!(decoded.fmt, ArmInstructionFormat::MULL_MLAL);
+        assert!(decoded.is_signed());
+        assert!(!decoded.is_accumulate());
+        assert!(decoded.is_set_cond());
+        assert_eq!(decoded.rd_hi(), 1);
+        assert_eq!(decoded.rd(), 2);
+        assert_eq!(decoded.rs(), 3);
+        assert_eq!(decoded.rm(), 4);
+    }
+
+    #[test]
+    fn ldr_str_hs_round_trips_with_immediate_offset() {
+        let offset = ArmHalfwordOffset::Immediate(0x3c);
+        let raw = encode_ldr_str_hs(
+            ArmCond::Always,
+            true,
+            true,
+            true,
+            false,
+            true,
+            true,
+            5,
+            6,
+            offset,
+        )
+        .unwrap();
+        let decoded = ArmInstruction::try_from((raw, 0)).unwrap();
+        assert_eq!(decoded.fmt, ArmInstructionFormat::LDR_STR_HS_IMM);
+        assert!(decoded.is_load());
+        assert!(decoded.is_signed());
+        assert!(decoded.is_halfword_transfer());
+        assert_eq!(decoded.rn(), 5);
+        assert_eq!(decoded.rd(), 6);
+        assert_eq!(decoded.hs_offset(), ArmHalfwordOffset::Immediate(0x3c));
+    }
+
+    #[test]
+    fn ldr_str_hs_round_trips_with_register_offset() {
+        let offset = ArmHalfwordOffset::Register(7);
+        let raw = encode_ldr_str_hs(
+            ArmCond::Always,
+            false,
+            true,
+            true,
+            false,
+            false,
+            true,
+            5,
+            6,
+            offset,
+        )
+        .unwrap();
+        let decoded = ArmInstruction::try_from((raw, 0)).unwrap();
+        assert_eq!(decoded.fmt, ArmInstructionFormat::LDR_STR_HS_REG);
+        assert!(!decoded.is_load());
+        assert!(!decoded.is_signed());
+        assert_eq!(decoded.hs_offset(), ArmHalfwordOffset::Register(7));
+    }
+
+    #[test]
+    fn ldm_stm_round_trips() {
+        let raw =
+            encode_ldm_stm(ArmCond::Always, true, true, true, false, true, 13, &[0, 1, 4, 14])
+                .unwrap();
+        let decoded = ArmInstruction::try_from((raw, 0)).unwrap();
+        assert_eq!(decoded.fmt, ArmInstructionFormat::LDM_STM);
+        assert!(decoded.is_load());
+        assert!(decoded.is_write_back());
+        assert_eq!(decoded.rn(), 13);
+        assert_eq!(decoded.register_list(), vec![0, 1, 4, 14]);
+    }
+
+    #[test]
+    fn swp_round_trips() {
+        let raw = encode_swp(ArmCond::Always, 1, 5, 6, 7).unwrap();
+        let decoded = ArmInstruction::try_from((raw, 0)).unwrap();
+        assert_eq!(decoded.fmt, ArmInstructionFormat::SWP);
+        assert_eq!(decoded.transfer_size(), 1);
+        assert_eq!(decoded.rn(), 5);
+        assert_eq!(decoded.rd(), 6);
+        assert_eq!(decoded.rm(), 7);
+    }
+
+    #[test]
+    fn mrs_round_trips() {
+        let raw = encode_mrs(ArmCond::Always, true, 9).unwrap();
+        let decoded = ArmInstruction::try_from((raw, 0)).unwrap();
+        assert_eq!(decoded.fmt, ArmInstructionFormat::MRS);
+        assert!(decoded.is_spsr());
+        assert_eq!(decoded.rd(), 9);
+    }
+
+    #[test]
+    fn msr_reg_round_trips() {
+        let raw = encode_msr_reg(ArmCond::Always, false, 3).unwrap();
+        let decoded = ArmInstruction::try_from((raw, 0)).unwrap();
+        assert_eq!(decoded.fmt, ArmInstructionFormat::MSR_REG);
+        assert!(!decoded.is_spsr());
+        assert_eq!(decoded.rm(), 3);
+    }
+
+    #[test]
+    fn msr_flags_round_trips_with_rotated_immediate() {
+        let operand2 = ArmInstructionShiftValue::RotatedImmediate(0xf0, 4);
+        let raw = encode_msr_flags(ArmCond::Always, true, operand2).unwrap();
+        let decoded = ArmInstruction::try_from((raw, 0)).unwrap();
+        assert_eq!(decoded.fmt, ArmInstructionFormat::MSR_FLAGS);
+        assert!(decoded.is_spsr());
+        assert_eq!(
+            decoded.operand2(),
+            ArmInstructionShiftValue::RotatedImmediate(0xf0, 4)
+        );
+    }
+
+    #[test]
+    fn encode_dp_rejects_out_of_range_shift_amount() {
+        let operand2 = ArmInstructionShiftValue::ShiftedRegister(
+            0,
+            ArmShift::ImmediateShift(40, ArmShiftType::LSL),
+        );
+        let err = encode_dp(ArmCond::Always, ArmOpCode::MOV, false, 0, 0, operand2).unwrap_err();
+        assert_eq!(err, ArmError::FieldOverflow("shift amount", 40));
+    }
+
+    #[test]
+    fn encode_dp_rejects_tst_teq_cmp_cmn_without_set_cond() {
+        // These bit patterns are reserved for MRS/MSR_REG/MSR_FLAGS; encoding them as DP
+        // would produce a word the decoder reclassifies as something else entirely.
+        for opcode in [ArmOpCode::TST, ArmOpCode::TEQ, ArmOpCode::CMP, ArmOpCode::CMN] {
+            let operand2 = ArmInstructionShiftValue::RotatedImmediate(0, 0);
+            let err = encode_dp(ArmCond::Always, opcode, false, 15, 0, operand2).unwrap_err();
+            assert_eq!(err, ArmError::FieldOverflow("set_cond", 0));
+        }
+    }
+
+    #[test]
+    fn decodes_swi() {
+        // cond=AL, bits 27..24=1111, comment=0x123456
+        let raw = 0xef12_3456;
+        let decoded = ArmInstruction::try_from((raw, 0)).unwrap();
+        assert_eq!(decoded.fmt, ArmInstructionFormat::SWI);
+        assert_eq!(decoded.swi_comment(), 0x123456);
+    }
+
+    #[test]
+    fn decodes_cdp() {
+        // cond=AL, bits 27..24=1110, bit4=0 (CDP, not MRC/MCR)
+        let raw = (0xe << 28) | (0xe << 24) | (0x7 << 20) | (14 << 8);
+        let decoded = ArmInstruction::try_from((raw, 0)).unwrap();
+        assert_eq!(decoded.fmt, ArmInstructionFormat::CDP);
+        assert_eq!(decoded.cp_opcode(), 0x7);
+        assert_eq!(decoded.cp_num(), 14);
+    }
+
+    #[test]
+    fn decodes_mrc_mcr() {
+        // Same bits 27..24 as CDP but with bit4=1, disambiguating MRC/MCR from CDP.
+        let raw = (0xe << 28) | (0xe << 24) | (0x7 << 21) | (14 << 8) | (1 << 4);
+        let decoded = ArmInstruction::try_from((raw, 0)).unwrap();
+        assert_eq!(decoded.fmt, ArmInstructionFormat::MRC_MCR);
+        assert_eq!(decoded.cp_info(), 0x7);
+        assert_eq!(decoded.cp_num(), 14);
+    }
+
+    #[test]
+    fn decodes_ldc_stc() {
+        // cond=AL, bits 27..25=110
+        let raw = 0xec12_3456;
+        let decoded = ArmInstruction::try_from((raw, 0)).unwrap();
+        assert_eq!(decoded.fmt, ArmInstructionFormat::LDC_STC);
+    }
+
+    #[test]
+    fn decodes_undefined() {
+        // cond=AL, bits 27..25=011, bit4=1 — reserved, not LDR_STR_HS_REG/CDP/MRC_MCR.
+        let raw = 0xe612_3410;
+        let decoded = ArmInstruction::try_from((raw, 0)).unwrap();
+        assert_eq!(decoded.fmt, ArmInstructionFormat::Undefined);
+    }
+
+    fn assert_rotated_immediate_round_trips(value: u32) {
+        let (imm, rot) = encode_rotated_immediate(value).unwrap();
+        let decoded = ArmInstructionShiftValue::RotatedImmediate(imm, rot)
+            .decode_rotated_immediate()
+            .unwrap();
+        assert_eq!(decoded as u32, value);
+    }
+
+    #[test]
+    fn encode_rotated_immediate_representable_values() {
+        assert_eq!(encode_rotated_immediate(0x000000FF), Some((0xFF, 0)));
+        assert_eq!(encode_rotated_immediate(0xFF000000), Some((0xFF, 8)));
+        assert_eq!(encode_rotated_immediate(0xF000000F), Some((0xFF, 4)));
+        assert_rotated_immediate_round_trips(0x000000FF);
+        assert_rotated_immediate_round_trips(0xFF000000);
+        assert_rotated_immediate_round_trips(0xF000000F);
+    }
+
+    #[test]
+    fn encode_rotated_immediate_rejects_unrepresentable_values() {
+        assert_eq!(encode_rotated_immediate(0x00FF00FF), None);
+    }
+
+    #[test]
+    fn encode_b_bl_rejects_overflowing_offset() {
+        let err = encode_b_bl(ArmCond::Always, false, i32::MIN).unwrap_err();
+        assert_eq!(err, ArmError::FieldOverflow("branch offset", i32::MIN as u32));
+    }
+
+    #[test]
+    fn arm_cond_passes_truth_table() {
+        use ArmCond::*;
+        // (cond, n, z, c, v) -> expected
+        let cases = [
+            (Equal, false, false, false, false, false),
+            (Equal, false, true, false, false, true),
+            (NotEqual, false, false, false, false, true),
+            (NotEqual, false, true, false, false, false),
+            (UnsignedHigherOrSame, false, false, false, false, false),
+            (UnsignedHigherOrSame, false, false, true, false, true),
+            (UnsignedLower, false, false, false, false, true),
+            (UnsignedLower, false, false, true, false, false),
+            (Negative, false, false, false, false, false),
+            (Negative, true, false, false, false, true),
+            (PositiveOrZero, false, false, false, false, true),
+            (PositiveOrZero, true, false, false, false, false),
+            (Overflow, false, false, false, false, false),
+            (Overflow, false, false, false, true, true),
+            (NoOverflow, false, false, false, false, true),
+            (NoOverflow, false, false, false, true, false),
+            (UnsignedHigher, false, false, true, false, true),
+            (UnsignedHigher, false, false, false, false, false),
+            (UnsignedHigher, false, true, true, false, false),
+            (UnsignedLowerOrSame, false, false, false, false, true),
+            (UnsignedLowerOrSame, false, true, true, false, true),
+            (UnsignedLowerOrSame, false, false, true, false, false),
+            (GreaterOrEqual, false, false, false, false, true),
+            (GreaterOrEqual, true, false, false, true, true),
+            (GreaterOrEqual, true, false, false, false, false),
+            (LessThan, false, false, false, false, false),
+            (LessThan, true, false, false, false, true),
+            (GreaterThan, false, false, false, false, true),
+            (GreaterThan, false, true, false, false, false),
+            (GreaterThan, true, false, false, true, true),
+            (GreaterThan, true, false, false, false, false),
+            (LessThanOrEqual, false, false, false, false, false),
+            (LessThanOrEqual, false, true, false, false, true),
+            (LessThanOrEqual, true, false, false, false, true),
+            (Always, true, true, true, true, true),
+            (Always, false, false, false, false, true),
+        ];
+        for (cond, n, z, c, v, expected) in cases {
+            assert_eq!(
+                cond.passes(n, z, c, v),
+                expected,
+                "{:?}.passes(n={}, z={}, c={}, v={}) expected {}",
+                cond,
+                n,
+                z,
+                c,
+                v,
+                expected
+            );
+        }
+    }
 }
@@ -0,0 +1,3 @@
+mod thumb_isa;
+
+pub use thumb_isa::*;
@@ -0,0 +1,366 @@
+use crate::arm::arm_isa::{ArmCond, ArmError};
+use crate::bit::BitIndex;
+use crate::num_traits::FromPrimitive;
+use std::convert::TryFrom;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[allow(non_camel_case_types)]
+pub enum ThumbInstructionFormat {
+    // Move shifted register
+    MOVE_SHIFTED_REG,
+    // Add/subtract
+    ADD_SUB,
+    // Move/compare/add/subtract immediate
+    MOV_CMP_ADD_SUB_IMM,
+    // ALU operations
+    ALU_OP,
+    // Hi register operations/branch exchange
+    HI_REG_OP_OR_BX,
+    // PC-relative load
+    PC_RELATIVE_LOAD,
+    // Load/store with register offset
+    LD_STR_REG_OFFSET,
+    // Load/store sign-extended byte/halfword
+    LD_STR_SIGN_EXT,
+    // Load/store with immediate offset
+    LD_STR_IMM_OFFSET,
+    // Load/store halfword
+    LD_STR_HALFWORD,
+    // SP-relative load/store
+    SP_RELATIVE_LD_STR,
+    // Load address
+    LOAD_ADDRESS,
+    // Add offset to stack pointer
+    ADD_OFFSET_TO_SP,
+    // Push/pop registers
+    PUSH_POP_REGS,
+    // Multiple load/store
+    MULTIPLE_LD_STR,
+    // Conditional branch
+    COND_BRANCH,
+    // Software interrupt
+    SWI,
+    // Unconditional branch
+    UNCOND_BRANCH,
+    // Long branch with link
+    LONG_BRANCH_WITH_LINK,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ThumbInstruction {
+    pub fmt: ThumbInstructionFormat,
+    pub raw: u16,
+    pub pc: u32,
+}
+
+impl TryFrom<(u16, u32)> for ThumbInstruction {
+    type Error = ArmError;
+
+    fn try_from(value: (u16, u32)) -> Result<Self, Self::Error> {
+        use ThumbInstructionFormat::*;
+        let (raw, addr) = value;
+
+        let fmt = if (0xff00 & raw) == 0xdf00 {
+            Ok(SWI)
+        } else if (0xf000 & raw) == 0xf000 {
+            Ok(LONG_BRANCH_WITH_LINK)
+        } else if (0xf800 & raw) == 0xe000 {
+            Ok(UNCOND_BRANCH)
+        } else if (0xf000 & raw) == 0xd000 {
+            Ok(COND_BRANCH)
+        } else if (0xf000 & raw) == 0xc000 {
+            Ok(MULTIPLE_LD_STR)
+        } else if (0xf600 & raw) == 0xb400 {
+            Ok(PUSH_POP_REGS)
+        } else if (0xff00 & raw) == 0xb000 {
+            Ok(ADD_OFFSET_TO_SP)
+        } else if (0xf000 & raw) == 0xa000 {
+            Ok(LOAD_ADDRESS)
+        } else if (0xf000 & raw) == 0x9000 {
+            Ok(SP_RELATIVE_LD_STR)
+        } else if (0xf000 & raw) == 0x8000 {
+            Ok(LD_STR_HALFWORD)
+        } else if (0xe000 & raw) == 0x6000 {
+            Ok(LD_STR_IMM_OFFSET)
+        } else if (0xf200 & raw) == 0x5200 {
+            Ok(LD_STR_SIGN_EXT)
+        } else if (0xf200 & raw) == 0x5000 {
+            Ok(LD_STR_REG_OFFSET)
+        } else if (0xf800 & raw) == 0x4800 {
+            Ok(PC_RELATIVE_LOAD)
+        } else if (0xfc00 & raw) == 0x4400 {
+            Ok(HI_REG_OP_OR_BX)
+        } else if (0xfc00 & raw) == 0x4000 {
+            Ok(ALU_OP)
+        } else if (0xe000 & raw) == 0x2000 {
+            Ok(MOV_CMP_ADD_SUB_IMM)
+        } else if (0xf800 & raw) == 0x1800 {
+            Ok(ADD_SUB)
+        } else if (0xe000 & raw) == 0x0000 {
+            Ok(MOVE_SHIFTED_REG)
+        } else {
+            Err(ArmError::UnknownInstructionFormat(raw as u32))
+        }?;
+
+        Ok(ThumbInstruction {
+            fmt: fmt,
+            raw: raw,
+            pc: addr,
+        })
+    }
+}
+
+impl ThumbInstruction {
+    pub fn rd(&self) -> usize {
+        use ThumbInstructionFormat::*;
+        match self.fmt {
+            PC_RELATIVE_LOAD | SP_RELATIVE_LD_STR | LOAD_ADDRESS | MOV_CMP_ADD_SUB_IMM => {
+                self.raw.bit_range(8..11) as usize
+            }
+            // H1 (bit 7) extends Rd/Hd into the full 0..16 register range.
+            HI_REG_OP_OR_BX => (self.raw.bit_range(0..3) as usize) | ((self.raw.bit(7) as usize) << 3),
+            _ => self.raw.bit_range(0..3) as usize,
+        }
+    }
+
+    pub fn rn(&self) -> usize {
+        use ThumbInstructionFormat::*;
+        match self.fmt {
+            MULTIPLE_LD_STR => self.raw.bit_range(8..11) as usize,
+            // H2 (bit 6) extends Rs/Hs into the full 0..16 register range.
+            HI_REG_OP_OR_BX => (self.raw.bit_range(3..6) as usize) | ((self.raw.bit(6) as usize) << 3),
+            _ => self.raw.bit_range(3..6) as usize,
+        }
+    }
+
+    /// Fallible version of [`ThumbInstruction::cond`]. Never panics on malformed input.
+    pub fn try_cond(&self) -> Result<ArmCond, ArmError> {
+        let cond_code = self.raw.bit_range(8..12) as u8;
+        ArmCond::from_u8(cond_code).ok_or(ArmError::UndefinedConditionCode(cond_code as u32))
+    }
+
+    /// The condition field of a conditional branch instruction (bits 8..12).
+    ///
+    /// Only meaningful when `self.fmt == ThumbInstructionFormat::COND_BRANCH`: any other
+    /// format may carry `0b1111` in this field (the reserved SWI encoding), which isn't a
+    /// valid `ArmCond`. Use [`ThumbInstruction::try_cond`] if that isn't guaranteed.
+    pub fn cond(&self) -> ArmCond {
+        assert_eq!(self.fmt, ThumbInstructionFormat::COND_BRANCH);
+        self.try_cond()
+            .expect("cond() called on a non-COND_BRANCH instruction")
+    }
+
+    /// The branch displacement for `COND_BRANCH`/`UNCOND_BRANCH`/`LONG_BRANCH_WITH_LINK`.
+    ///
+    /// For `LONG_BRANCH_WITH_LINK` this is just the raw unsigned 11-bit offset field
+    /// (bits 0..11), not a usable displacement: the BL pair's H=0/H=1 halves (bit 11 of
+    /// `raw`) aren't distinguished by `fmt`, and this format carries no sign extension.
+    /// Callers need both halves combined and sign-extended from bit 10 of the H=0 word;
+    /// inspect `raw` directly rather than relying on this accessor for that format.
+    pub fn branch_offset(&self) -> i32 {
+        use ThumbInstructionFormat::*;
+        match self.fmt {
+            COND_BRANCH => ((((self.raw as i32) << 24) >> 24) << 1) + 4,
+            UNCOND_BRANCH => ((((self.raw as i32) << 21) >> 21) << 1) + 4,
+            LONG_BRANCH_WITH_LINK => self.raw.bit_range(0..11) as i32,
+            _ => 0,
+        }
+    }
+
+    pub fn register_list(&self) -> Vec<usize> {
+        let list_bits = self.raw & 0xff;
+        let mut list = Vec::with_capacity(8);
+        for i in 0..8 {
+            if (list_bits & (1 << i)) != 0 {
+                list.push(i)
+            }
+        }
+        list
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(raw: u16) -> ThumbInstruction {
+        ThumbInstruction::try_from((raw, 0)).unwrap()
+    }
+
+    #[test]
+    fn decodes_move_shifted_reg() {
+        let raw: u16 = (5 << 6) | (3 << 3) | 2; // offset5=5, Rs=3, Rd=2
+        let insn = decode(raw);
+        assert_eq!(insn.fmt, ThumbInstructionFormat::MOVE_SHIFTED_REG);
+        assert_eq!(insn.rd(), 2);
+        assert_eq!(insn.rn(), 3);
+    }
+
+    #[test]
+    fn decodes_add_sub() {
+        let raw: u16 = 0x1800 | (1 << 3) | 6; // Rs=1, Rd=6
+        let insn = decode(raw);
+        assert_eq!(insn.fmt, ThumbInstructionFormat::ADD_SUB);
+        assert_eq!(insn.rd(), 6);
+        assert_eq!(insn.rn(), 1);
+    }
+
+    #[test]
+    fn decodes_mov_cmp_add_sub_imm() {
+        let raw: u16 = 0x2000 | (7 << 8) | 0x42; // Rd=7, imm8=0x42
+        let insn = decode(raw);
+        assert_eq!(insn.fmt, ThumbInstructionFormat::MOV_CMP_ADD_SUB_IMM);
+        assert_eq!(insn.rd(), 7);
+    }
+
+    #[test]
+    fn decodes_alu_op() {
+        let raw: u16 = 0x4000 | (5 << 3) | 3; // Rs=5, Rd=3
+        let insn = decode(raw);
+        assert_eq!(insn.fmt, ThumbInstructionFormat::ALU_OP);
+        assert_eq!(insn.rd(), 3);
+        assert_eq!(insn.rn(), 5);
+    }
+
+    #[test]
+    fn decodes_hi_reg_op_or_bx_folds_h1_h2_into_rd_rn() {
+        // H1=1, H2=0, Rs=2, Rd=3 -> rd should be 3|0b1000=11, rn should stay 2.
+        let raw: u16 = 0x4400 | (1 << 7) | (0 << 6) | (2 << 3) | 3;
+        let insn = decode(raw);
+        assert_eq!(insn.fmt, ThumbInstructionFormat::HI_REG_OP_OR_BX);
+        assert_eq!(insn.rd(), 11);
+        assert_eq!(insn.rn(), 2);
+    }
+
+    #[test]
+    fn decodes_pc_relative_load() {
+        let raw: u16 = 0x4800 | (5 << 8) | 0x20; // Rd=5, word8=0x20
+        let insn = decode(raw);
+        assert_eq!(insn.fmt, ThumbInstructionFormat::PC_RELATIVE_LOAD);
+        assert_eq!(insn.rd(), 5);
+    }
+
+    #[test]
+    fn decodes_ld_str_reg_offset() {
+        let raw: u16 = 0x5000 | (2 << 6) | (3 << 3) | 4; // Ro=2, Rb=3, Rd=4, bit9=0
+        let insn = decode(raw);
+        assert_eq!(insn.fmt, ThumbInstructionFormat::LD_STR_REG_OFFSET);
+        assert_eq!(insn.rd(), 4);
+        assert_eq!(insn.rn(), 3);
+    }
+
+    #[test]
+    fn decodes_ld_str_sign_ext() {
+        let raw: u16 = 0x5200 | (3 << 6) | (2 << 3) | 1; // Ro=3, Rb=2, Rd=1, bit9=1
+        let insn = decode(raw);
+        assert_eq!(insn.fmt, ThumbInstructionFormat::LD_STR_SIGN_EXT);
+        assert_eq!(insn.rd(), 1);
+        assert_eq!(insn.rn(), 2);
+    }
+
+    #[test]
+    fn decodes_ld_str_imm_offset() {
+        let raw: u16 = 0x6000 | (7 << 6) | (2 << 3) | 5; // offset5=7, Rb=2, Rd=5
+        let insn = decode(raw);
+        assert_eq!(insn.fmt, ThumbInstructionFormat::LD_STR_IMM_OFFSET);
+        assert_eq!(insn.rd(), 5);
+        assert_eq!(insn.rn(), 2);
+    }
+
+    #[test]
+    fn decodes_ld_str_halfword() {
+        let raw: u16 = 0x8000 | (4 << 3) | 3; // Rb=4, Rd=3
+        let insn = decode(raw);
+        assert_eq!(insn.fmt, ThumbInstructionFormat::LD_STR_HALFWORD);
+        assert_eq!(insn.rd(), 3);
+        assert_eq!(insn.rn(), 4);
+    }
+
+    #[test]
+    fn decodes_sp_relative_ld_str() {
+        let raw: u16 = 0x9000 | (6 << 8) | 0x10; // Rd=6, word8=0x10
+        let insn = decode(raw);
+        assert_eq!(insn.fmt, ThumbInstructionFormat::SP_RELATIVE_LD_STR);
+        assert_eq!(insn.rd(), 6);
+    }
+
+    #[test]
+    fn decodes_load_address() {
+        let raw: u16 = 0xa000 | (2 << 8) | 0x44; // Rd=2, word8=0x44
+        let insn = decode(raw);
+        assert_eq!(insn.fmt, ThumbInstructionFormat::LOAD_ADDRESS);
+        assert_eq!(insn.rd(), 2);
+    }
+
+    #[test]
+    fn decodes_add_offset_to_sp() {
+        let raw: u16 = 0xb000 | 0x10; // SWord7=0x10
+        let insn = decode(raw);
+        assert_eq!(insn.fmt, ThumbInstructionFormat::ADD_OFFSET_TO_SP);
+    }
+
+    #[test]
+    fn decodes_push_pop_regs() {
+        let raw: u16 = 0xb400 | 0b0000_0101; // Rlist = r0, r2
+        let insn = decode(raw);
+        assert_eq!(insn.fmt, ThumbInstructionFormat::PUSH_POP_REGS);
+        assert_eq!(insn.register_list(), vec![0, 2]);
+    }
+
+    #[test]
+    fn decodes_multiple_ld_str() {
+        let raw: u16 = 0xc000 | (3 << 8) | 0b0000_0011; // Rb=3, Rlist = r0, r1
+        let insn = decode(raw);
+        assert_eq!(insn.fmt, ThumbInstructionFormat::MULTIPLE_LD_STR);
+        assert_eq!(insn.rn(), 3);
+        assert_eq!(insn.register_list(), vec![0, 1]);
+    }
+
+    #[test]
+    fn decodes_cond_branch_with_positive_offset() {
+        let raw: u16 = 0xd000 | 0x02; // cond=Equal, soffset8=2
+        let insn = decode(raw);
+        assert_eq!(insn.fmt, ThumbInstructionFormat::COND_BRANCH);
+        assert_eq!(insn.cond(), ArmCond::Equal);
+        assert_eq!(insn.try_cond(), Ok(ArmCond::Equal));
+        assert_eq!(insn.branch_offset(), 8);
+    }
+
+    #[test]
+    fn cond_branch_offset_sign_extends_negative_values() {
+        let raw: u16 = 0xd000 | 0xfe; // soffset8=-2
+        let insn = decode(raw);
+        assert_eq!(insn.branch_offset(), 0);
+    }
+
+    #[test]
+    fn decodes_swi() {
+        let raw: u16 = 0xdf00 | 0x01;
+        let insn = decode(raw);
+        assert_eq!(insn.fmt, ThumbInstructionFormat::SWI);
+    }
+
+    #[test]
+    fn decodes_uncond_branch_with_negative_offset() {
+        let raw: u16 = 0xe000 | 0x7ff; // offset11 = -1
+        let insn = decode(raw);
+        assert_eq!(insn.fmt, ThumbInstructionFormat::UNCOND_BRANCH);
+        assert_eq!(insn.branch_offset(), 2);
+    }
+
+    #[test]
+    fn decodes_long_branch_with_link() {
+        let raw: u16 = 0xf000 | 0x123;
+        let insn = decode(raw);
+        assert_eq!(insn.fmt, ThumbInstructionFormat::LONG_BRANCH_WITH_LINK);
+        assert_eq!(insn.branch_offset(), 0x123);
+    }
+
+    #[test]
+    fn cond_panics_on_non_branch_format() {
+        let raw: u16 = 0x4000; // ALU_OP
+        let insn = decode(raw);
+        let result = std::panic::catch_unwind(|| insn.cond());
+        assert!(result.is_err());
+    }
+}